@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use num_traits::identities::Zero;
 
 use engine_core::engine_state::{
@@ -6,8 +8,8 @@ use engine_core::engine_state::{
     CONV_RATE,
 };
 
-use engine_shared::{account::Account, motes::Motes};
-use types::{AccessRights, Key, URef, U512};
+use engine_shared::{account::Account, motes::Motes, transform::Transform};
+use types::{AccessRights, Gas, Key, URef, U512};
 
 use crate::{
     internal::{InMemoryWasmTestBuilder, DEFAULT_GENESIS_CONFIG, DEFAULT_GENESIS_CONFIG_HASH},
@@ -17,6 +19,20 @@ use crate::{
 /// Context in which to run a test of a Wasm smart contract.
 pub struct TestContext {
     inner: InMemoryWasmTestBuilder,
+    block_time: u64,
+}
+
+/// An opaque handle to a frozen global state captured by [`TestContext::checkpoint`].
+///
+/// Pass it back to [`TestContext::restore`] to rewind the working state to the point at which it
+/// was taken, discarding any [`run`](TestContext::run)s performed in between. The handle owns a
+/// branched copy of the in-memory state, so restoring re-roots subsequent `exec`s at the captured
+/// point rather than relying on mutating the builder's post-state hash in place. The simulated
+/// block time is captured alongside the state so [`restore`](TestContext::restore) rewinds the
+/// clock too.
+pub struct StateHandle {
+    inner: InMemoryWasmTestBuilder,
+    block_time: u64,
 }
 
 impl TestContext {
@@ -48,7 +64,7 @@ impl TestContext {
                 let maybe_target_initial_balance =
                     self.maybe_purse_balance(session_transfer_info.maybe_target_purse);
 
-                let builder = self.inner.exec(session.inner);
+                let builder = self.inner.exec(session.inner.with_block_time(self.block_time));
                 if session.expect_success {
                     builder.expect_success();
                 }
@@ -88,7 +104,7 @@ impl TestContext {
                 }
             }
             None => {
-                let builder = self.inner.exec(session.inner);
+                let builder = self.inner.exec(session.inner.with_block_time(self.block_time));
                 if session.expect_success {
                     builder.expect_success();
                 }
@@ -100,17 +116,172 @@ impl TestContext {
         self
     }
 
+    /// Runs the supplied [`Session`]s in order against a single evolving working state, committing
+    /// each deploy's transforms as it executes, and returns a [`SessionResult`] per deploy.
+    ///
+    /// This is the batch analogue of [`run`](Self::run): rather than `panic!`ing on the first
+    /// failure or unbalanced transfer, each outcome is captured so the whole sequence (e.g. fund,
+    /// stake, transfer) can be asserted over. Committing per deploy is what lets later deploys in
+    /// the batch observe the effects of earlier ones.
+    ///
+    /// Note: unlike [`run`](Self::run), the batch path always commits and never checks for success,
+    /// so each [`Session`]'s `commit` and `expect_success` flags are ignored.
+    pub fn run_batch(&mut self, sessions: Vec<Session>) -> Vec<SessionResult> {
+        let mut results = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            let maybe_transfer_info = session.check_transfer_success.clone();
+            let maybe_source_initial = maybe_transfer_info.as_ref().map(|info| {
+                self.maybe_purse_balance(Some(info.source_purse))
+                    .expect("source purse balance")
+            });
+            let maybe_target_initial = maybe_transfer_info
+                .as_ref()
+                .and_then(|info| self.maybe_purse_balance(info.maybe_target_purse));
+
+            let builder = self.inner.exec(session.inner.with_block_time(self.block_time));
+            let success = !builder.is_error();
+            let gas_cost = builder.last_exec_gas_cost();
+            builder.commit();
+
+            let transfer_verified = maybe_transfer_info.map(|info| {
+                let source_initial = maybe_source_initial.expect("source initial balance");
+                let source_ending = self
+                    .maybe_purse_balance(Some(info.source_purse))
+                    .expect("source ending balance");
+                let expected_source_ending = source_initial
+                    - Motes::new(info.transfer_amount)
+                    - Motes::from_gas(gas_cost, CONV_RATE).expect("motes from gas");
+                let source_ok = source_ending == expected_source_ending;
+
+                let target_ok = match (maybe_target_initial, info.maybe_target_purse) {
+                    (Some(target_initial), Some(target_purse)) => {
+                        let target_ending = self
+                            .maybe_purse_balance(Some(target_purse))
+                            .expect("target ending balance");
+                        target_ending == target_initial + Motes::new(info.transfer_amount)
+                    }
+                    _ => true,
+                };
+
+                source_ok && target_ok
+            });
+
+            results.push(SessionResult {
+                success,
+                gas_cost,
+                transfer_verified,
+            });
+        }
+        results
+    }
+
+    /// Advances the simulated clock by `delta` milliseconds, applied as the block time of
+    /// subsequent [`run`](Self::run) and [`run_batch`](Self::run_batch) calls.
+    pub fn advance_block_time(&mut self, delta: u64) {
+        self.block_time += delta;
+    }
+
+    /// Advances the simulated clock forward by `era_duration` milliseconds, so subsequent deploys
+    /// observe a block time one era later.
+    ///
+    /// This is a pure clock bump for contracts that *read* block time across an era boundary; it
+    /// does not run an era-end/`step` request, so on-chain auction settlement is not triggered. The
+    /// era length is supplied by the caller because it is chain-configurable rather than a fixed
+    /// constant.
+    pub fn advance_era(&mut self, era_duration: u64) {
+        self.advance_block_time(era_duration);
+    }
+
+    /// Captures the current post-commit global state root as a [`StateHandle`].
+    ///
+    /// A sequence of [`run`](Self::run)s can then be executed speculatively and later discarded by
+    /// passing the handle to [`restore`](Self::restore), without rebuilding genesis.
+    pub fn checkpoint(&self) -> StateHandle {
+        StateHandle {
+            inner: self.inner.clone(),
+            block_time: self.block_time,
+        }
+    }
+
+    /// Resets the working state back to the point captured by a previous
+    /// [`checkpoint`](Self::checkpoint).
+    ///
+    /// Any transforms committed since the handle was taken are abandoned, and subsequent
+    /// [`run`](Self::run)s re-root at the captured state. The simulated block time is also rewound
+    /// to its value at checkpoint time, so a clock advanced in between does not leak past the
+    /// restore.
+    pub fn restore(&mut self, handle: StateHandle) {
+        self.inner = handle.inner;
+        self.block_time = handle.block_time;
+    }
+
     /// Queries for a [`Value`] stored under the given `key` and `path`.
     ///
     /// Returns an [`Error`] if not found.
     pub fn query<T: AsRef<str>>(&self, key: PublicKey, path: &[T]) -> Result<Value> {
+        self.query_key(Key::Account(key), path)
+    }
+
+    /// Queries for a [`Value`] stored under an arbitrary base [`Key`] and `path`.
+    ///
+    /// Unlike [`query`](Self::query), the base may be a contract hash or a bare [`URef`] rather
+    /// than only an account, allowing a test to follow references into contract-owned state.
+    ///
+    /// Returns an [`Error`] if not found.
+    pub fn query_key<T: AsRef<str>>(&self, base: Key, path: &[T]) -> Result<Value> {
         let path = path.iter().map(AsRef::as_ref).collect::<Vec<_>>();
         self.inner
-            .query(None, Key::Account(key), &path)
+            .query(None, base, &path)
             .map(Value::new)
             .map_err(Error::from)
     }
 
+    /// Returns all named keys registered under the account or contract at `base`.
+    ///
+    /// This lets a test introspect a deployed contract's named-key table and follow the entries
+    /// into contract-owned state. An empty map is returned if `base` names neither an account nor a
+    /// contract.
+    pub fn named_keys(&self, base: Key) -> BTreeMap<String, Key> {
+        match base {
+            Key::Account(public_key) => self
+                .inner
+                .get_account(public_key)
+                .map(|account| account.named_keys().clone())
+                .unwrap_or_default(),
+            Key::Hash(hash) => self
+                .inner
+                .get_contract(hash)
+                .map(|contract| contract.named_keys().clone())
+                .unwrap_or_default(),
+            _ => BTreeMap::new(),
+        }
+    }
+
+    /// Returns the gas cost and execution effects produced by the most recent [`run`](Self::run),
+    /// or `None` if no deploy has been executed or the most recent one was run without committing.
+    ///
+    /// `get_transforms` grows on commit while the gas cost reflects the latest exec, so the two
+    /// would describe different executions if the last exec had not been committed. Requiring the
+    /// exec and commit counts to match rules that out, guaranteeing both halves describe the same
+    /// execution.
+    pub fn last_exec_result(&self) -> Option<ExecResult> {
+        let transforms = self.inner.get_transforms();
+        if transforms.len() != self.inner.get_exec_responses().len() {
+            return None;
+        }
+        let effects = transforms.last()?.clone().into_iter().collect();
+        Some(ExecResult {
+            gas_cost: self.inner.last_exec_gas_cost(),
+            effects,
+        })
+    }
+
+    // NOTE: `get_messages` (request chunk0-3) is intentionally not implemented. Contract-emitted
+    // messages, the `Key::Message`/`MessageAddr`/`TopicNameHash` types, and the key enumeration
+    // needed to scan for them do not exist in this engine version -- the messaging feature postdates
+    // this `types`/`engine_shared`/`engine-test-support` layout. There is no committed state to read
+    // messages from here, so no assertion surface can be provided without fabricating the feature.
+
     /// Gets the balance of the purse under the given [`URefAddr`].
     ///
     /// Note that this requires performing an earlier query to retrieve `purse_addr`.
@@ -134,9 +305,61 @@ impl TestContext {
     }
 }
 
+/// The gas cost and execution effects of the most recent [`TestContext::run`].
+///
+/// The effects are the set of [`Transform`]s keyed by the [`Key`] they act on, so a test can
+/// enumerate precisely which keys changed and to what, rather than re-querying individual keys and
+/// balances after the fact.
+pub struct ExecResult {
+    gas_cost: Gas,
+    effects: BTreeMap<Key, Transform>,
+}
+
+impl ExecResult {
+    /// Returns the gas consumed by the deploy.
+    pub fn gas_cost(&self) -> Gas {
+        self.gas_cost
+    }
+
+    /// Returns the execution effects keyed by the [`Key`] each transform acts on.
+    pub fn effects(&self) -> &BTreeMap<Key, Transform> {
+        &self.effects
+    }
+}
+
+/// Outcome of a single [`Session`] executed as part of a [`TestContext::run_batch`] call.
+///
+/// Unlike [`run`](TestContext::run), which `panic!`s as soon as a deploy fails or a transfer does
+/// not balance, a `SessionResult` records the outcome so a multi-deploy scenario can be asserted
+/// over as a whole.
+pub struct SessionResult {
+    success: bool,
+    gas_cost: Gas,
+    transfer_verified: Option<bool>,
+}
+
+impl SessionResult {
+    /// Returns `true` if the deploy executed without error.
+    pub fn is_success(&self) -> bool {
+        self.success
+    }
+
+    /// Returns the gas consumed by the deploy.
+    pub fn gas_cost(&self) -> Gas {
+        self.gas_cost
+    }
+
+    /// For sessions carrying [`check_transfer_success`](Session), returns whether the observed
+    /// balance deltas matched the expected transfer (accounting for gas); `None` otherwise.
+    pub fn transfer_verified(&self) -> Option<bool> {
+        self.transfer_verified
+    }
+}
+
 /// Builder for a [`TestContext`].
 pub struct TestContextBuilder {
     genesis_config: GenesisConfig,
+    block_time: u64,
 }
 
 impl TestContextBuilder {
@@ -147,9 +370,17 @@ impl TestContextBuilder {
     pub fn new() -> Self {
         TestContextBuilder {
             genesis_config: DEFAULT_GENESIS_CONFIG.clone(),
+            block_time: 0,
         }
     }
 
+    /// Returns `self` with the initial block time (in milliseconds) used for the first deploys run
+    /// against the built [`TestContext`].
+    pub fn with_block_time(mut self, block_time: u64) -> Self {
+        self.block_time = block_time;
+        self
+    }
+
     /// Returns `self` with the provided account's details added to existing ones, for inclusion in
     /// the Genesis block.
     ///
@@ -162,6 +393,28 @@ impl TestContextBuilder {
         self
     }
 
+    /// Returns `self` with the provided validator's details added to existing ones, for inclusion
+    /// in the Genesis block.
+    ///
+    /// Unlike [`with_account`](Self::with_account), the resulting [`GenesisAccount`] is created with
+    /// the supplied `bonded_amount` rather than zero, so a non-zero amount stands the genesis state
+    /// up with the validator already staked. The amount is not checked; zero is accepted.
+    ///
+    /// Note: both `balance` and `bonded_amount` represent numbers of motes.
+    pub fn with_validator(
+        mut self,
+        address: PublicKey,
+        balance: U512,
+        bonded_amount: U512,
+    ) -> Self {
+        let new_account =
+            GenesisAccount::new(address, Motes::new(balance), Motes::new(bonded_amount));
+        self.genesis_config
+            .ee_config_mut()
+            .push_account(new_account);
+        self
+    }
+
     /// Builds the [`TestContext`].
     pub fn build(self) -> TestContext {
         let mut inner = InMemoryWasmTestBuilder::default();
@@ -171,7 +424,10 @@ impl TestContextBuilder {
             self.genesis_config.take_ee_config(),
         );
         inner.run_genesis(&run_genesis_request);
-        TestContext { inner }
+        TestContext {
+            inner,
+            block_time: self.block_time,
+        }
     }
 }
 